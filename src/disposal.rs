@@ -0,0 +1,213 @@
+//! What to do with a file once it's been confirmed as a duplicate.
+//!
+//! Calling `remove_file` directly is destructive and a single permission
+//! error panics the whole run. `Disposal` centralizes the three ways a
+//! duplicate can be handled (delete, dry-run, or quarantine) and reports
+//! failures instead of panicking, so one bad file doesn't take down an
+//! otherwise-successful pass over the rest of the tree.
+
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Verbosity level for per-file disposal logging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only print the final summary (plus, under `--dry-run`, the one line per
+    /// file that would be removed).
+    Quiet,
+    /// Also print one line per duplicate as it's handled.
+    PerFile,
+    /// Also print extra detail (e.g. file size) alongside that line.
+    Debug,
+}
+
+impl Verbosity {
+    pub fn from_count(count: u8) -> Self {
+        match count {
+            0 => Verbosity::Quiet,
+            1 => Verbosity::PerFile,
+            _ => Verbosity::Debug,
+        }
+    }
+}
+
+/// How duplicate files should be disposed of.
+pub struct Disposal {
+    dry_run: bool,
+    output: Option<PathBuf>,
+    verbose: Verbosity,
+}
+
+impl Disposal {
+    pub fn new(dry_run: bool, output: Option<PathBuf>, verbose: Verbosity) -> Self {
+        Self { dry_run, output, verbose }
+    }
+
+    /// Dispose of `path`, which has been confirmed as a duplicate.
+    ///
+    /// With `--dry-run` this only logs what would happen. With `--output`
+    /// set, the file is moved into that directory instead of deleted. A
+    /// rename is tried first and a copy-then-delete is used as a fallback so
+    /// moving across filesystems still works.
+    pub fn dispose(&self, path: &Path) -> io::Result<()> {
+        if self.dry_run {
+            println!("[dry-run] would remove {path:?}");
+            self.log_size(path);
+            return Ok(());
+        }
+
+        if let Some(output) = &self.output {
+            return self.quarantine(path, output);
+        }
+
+        if self.verbose >= Verbosity::PerFile {
+            println!("Removing {path:?}");
+        }
+        self.log_size(path);
+        std::fs::remove_file(path)
+    }
+
+    fn quarantine(&self, path: &Path, output: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(output)?;
+
+        let file_name = path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "path has no file name")
+        })?;
+
+        // Reserve the destination name atomically so two workers disposing
+        // of unrelated files that share a basename can't both resolve to the
+        // same free-looking path and have one silently clobber the other via
+        // POSIX rename semantics.
+        let (dest, reservation) = reserve_destination(output, file_name.as_ref())?;
+
+        if self.verbose >= Verbosity::PerFile {
+            println!("Moving {path:?} -> {dest:?}");
+        }
+        self.log_size(path);
+
+        // Drop the reservation handle before filling it in: the rename
+        // replaces it atomically, and the copy fallback truncates it.
+        drop(reservation);
+
+        match std::fs::rename(path, &dest) {
+            Ok(()) => Ok(()),
+            // Renaming across filesystems fails; fall back to copy + delete.
+            Err(_) => {
+                std::fs::copy(path, &dest)?;
+                std::fs::remove_file(path)
+            }
+        }
+    }
+
+    fn log_size(&self, path: &Path) {
+        if self.verbose >= Verbosity::Debug {
+            if let Ok(metadata) = path.metadata() {
+                println!("  size: {} bytes", metadata.len());
+            }
+        }
+    }
+}
+
+/// Atomically claim a destination path under `dir` for `file_name`,
+/// disambiguating with a numeric suffix if a file by that name already
+/// exists (or was just claimed by another worker in the same race). Returns
+/// the claimed path along with the open handle used to claim it via
+/// `create_new`, so the caller can hold the reservation until it's ready to
+/// fill (or replace) the file.
+fn reserve_destination(dir: &Path, file_name: &Path) -> io::Result<(PathBuf, File)> {
+    let stem = file_name.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let ext = file_name.extension().map(|e| e.to_string_lossy().into_owned());
+
+    let mut candidate = dir.join(file_name);
+    let mut suffix = 0u64;
+
+    loop {
+        match File::options().write(true).create_new(true).open(&candidate) {
+            Ok(file) => return Ok((candidate, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                suffix += 1;
+                let name = match &ext {
+                    Some(ext) => format!("{stem}-{suffix}.{ext}"),
+                    None => format!("{stem}-{suffix}"),
+                };
+                candidate = dir.join(name);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+    use std::io::Write as _;
+
+    fn write_file(path: &Path, contents: &[u8]) {
+        File::create(path).unwrap().write_all(contents).unwrap();
+    }
+
+    #[test]
+    fn dry_run_leaves_file_in_place() {
+        let dir = scratch_dir("dry_run");
+        let path = dir.join("dup.txt");
+        write_file(&path, b"data");
+
+        Disposal::new(true, None, Verbosity::Quiet).dispose(&path).unwrap();
+
+        assert!(path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn delete_removes_file() {
+        let dir = scratch_dir("delete");
+        let path = dir.join("dup.txt");
+        write_file(&path, b"data");
+
+        Disposal::new(false, None, Verbosity::Quiet).dispose(&path).unwrap();
+
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn quarantine_moves_file_into_output_dir() {
+        let dir = scratch_dir("quarantine_src");
+        let output = scratch_dir("quarantine_out");
+        let path = dir.join("dup.txt");
+        write_file(&path, b"data");
+
+        Disposal::new(false, Some(output.clone()), Verbosity::Quiet).dispose(&path).unwrap();
+
+        assert!(!path.exists());
+        assert!(output.join("dup.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(&output);
+    }
+
+    #[test]
+    fn reserve_destination_disambiguates_existing_names() {
+        let output = scratch_dir("reserve");
+        write_file(&output.join("dup.txt"), b"already here");
+
+        let (dest, _reservation) = reserve_destination(&output, Path::new("dup.txt")).unwrap();
+
+        assert_eq!(dest, output.join("dup-1.txt"));
+        let _ = std::fs::remove_dir_all(&output);
+    }
+
+    #[test]
+    fn reserve_destination_skips_multiple_existing_suffixes() {
+        let output = scratch_dir("reserve_multi");
+        write_file(&output.join("dup.txt"), b"first");
+        write_file(&output.join("dup-1.txt"), b"second");
+
+        let (dest, _reservation) = reserve_destination(&output, Path::new("dup.txt")).unwrap();
+
+        assert_eq!(dest, output.join("dup-2.txt"));
+        let _ = std::fs::remove_dir_all(&output);
+    }
+}
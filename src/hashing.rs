@@ -0,0 +1,152 @@
+//! Hashing of file contents into the `BlakeHasher` digest used to find
+//! candidate duplicates.
+//!
+//! Files are read in fixed-size chunks through a `BufReader` so memory stays
+//! bounded no matter how large the file is, instead of loading the whole
+//! thing into RAM with `std::fs::read`. Files at or above `mmap_threshold`
+//! bytes are hashed straight out of a memory mapping instead, which avoids
+//! the extra copy through a read buffer for large files. Files at or above
+//! `parallel_threshold` go further still: the mapped region is split into
+//! fixed-size segments that are hashed across a rayon pool, so one huge file
+//! doesn't bottleneck a single core while the others sit idle.
+
+use blake2::{Blake2b, Digest, digest::consts::U16};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+pub type BlakeHasher = Blake2b<U16>;
+
+const STREAM_CHUNK_SIZE: usize = 128 * 1024;
+const SEGMENT_SIZE: usize = 16 * 1024 * 1024;
+
+/// Hash the contents of `path`. The strategy scales with file size:
+/// a plain buffered stream below `mmap_threshold`, a single-threaded memory
+/// mapping below `parallel_threshold`, and a rayon-parallel, segmented
+/// memory mapping above it.
+///
+/// Returns the digest along with the number of bytes read, so callers can
+/// feed it straight into progress reporting without a second `stat`.
+pub fn hash_file(
+    path: &Path,
+    mmap_threshold: u64,
+    parallel_threshold: u64,
+) -> io::Result<(Vec<u8>, u64)> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let digest = if len >= parallel_threshold {
+        // SAFETY: the file is not expected to be concurrently truncated by
+        // another process while this tool is deduping it.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        hash_segments_parallel(&mmap)
+    } else if len >= mmap_threshold {
+        // SAFETY: same as above.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        let mut hasher = BlakeHasher::new();
+        hasher.update(&mmap);
+        hasher.finalize().to_vec()
+    } else {
+        let mut reader = BufReader::new(file);
+        let mut buf = [0u8; STREAM_CHUNK_SIZE];
+        let mut hasher = BlakeHasher::new();
+
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+
+        hasher.finalize().to_vec()
+    };
+
+    Ok((digest, len))
+}
+
+/// Hash `data` in `SEGMENT_SIZE` chunks across a rayon pool, then hash the
+/// ordered concatenation of the per-segment digests to produce the final
+/// key. Blake2b isn't natively tree-structured, so this scheme only needs to
+/// be deterministic and collision-resistant, not match any external format.
+fn hash_segments_parallel(data: &[u8]) -> Vec<u8> {
+    let sub_digests: Vec<Vec<u8>> = data
+        .par_chunks(SEGMENT_SIZE)
+        .map(|segment| {
+            let mut hasher = BlakeHasher::new();
+            hasher.update(segment);
+            hasher.finalize().to_vec()
+        })
+        .collect();
+
+    let mut hasher = BlakeHasher::new();
+    for sub_digest in sub_digests {
+        hasher.update(&sub_digest);
+    }
+    hasher.finalize().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_temp;
+
+    // Large enough to cross both the mmap and parallel-segment thresholds
+    // used below, and not an exact multiple of SEGMENT_SIZE so the final,
+    // short segment is exercised too.
+    const BIG_LEN: usize = SEGMENT_SIZE * 2 + 17;
+
+    #[test]
+    fn buffered_path_matches_itself_across_two_reads() {
+        let path = write_temp("buffered", b"hello world");
+
+        let (first, len) = hash_file(&path, u64::MAX, u64::MAX).unwrap();
+        let (second, _) = hash_file(&path, u64::MAX, u64::MAX).unwrap();
+
+        assert_eq!(len, 11);
+        assert_eq!(first, second);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn mmap_path_matches_buffered_path_for_the_same_content() {
+        let contents = vec![0x7au8; 4096];
+        let path = write_temp("mmap", &contents);
+
+        let (buffered, _) = hash_file(&path, u64::MAX, u64::MAX).unwrap();
+        let (mmapped, _) = hash_file(&path, 0, u64::MAX).unwrap();
+
+        // The parallel scheme hashes hashes of segments rather than the raw
+        // bytes, so it isn't expected to match the other two; but buffered
+        // and single-threaded mmap both hash the same bytes the same way.
+        assert_eq!(buffered, mmapped);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn parallel_segmented_path_is_consistent_for_matching_content() {
+        let contents = vec![0x99u8; BIG_LEN];
+        let a = write_temp("parallel_a", &contents);
+        let b = write_temp("parallel_b", &contents);
+
+        let (digest_a, len_a) = hash_file(&a, 0, 0).unwrap();
+        let (digest_b, len_b) = hash_file(&b, 0, 0).unwrap();
+
+        // This is exactly what the rest of the pipeline relies on: two files
+        // with matching content land in the same `BTreeMap` bucket because
+        // their digests compare equal, regardless of which threshold sent
+        // them down the parallel-segmented path.
+        assert_eq!(len_a, len_b);
+        assert_eq!(digest_a, digest_b);
+        let _ = std::fs::remove_file(a);
+        let _ = std::fs::remove_file(b);
+    }
+
+    #[test]
+    fn hash_segments_parallel_is_consistent_for_matching_data() {
+        let data = vec![0x11u8; BIG_LEN];
+
+        assert_eq!(hash_segments_parallel(&data), hash_segments_parallel(&data));
+    }
+}
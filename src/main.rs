@@ -1,10 +1,21 @@
+mod disposal;
+mod hashing;
+mod progress;
+mod scheduler;
+mod sizes;
+#[cfg(test)]
+mod test_support;
+mod verify;
+
 use clap::Parser;
-use blake2::{Blake2b, Digest, digest::consts::U16};
 use core_affinity::CoreId;
-
-use std::fs::DirEntry;
+use crossbeam_deque::{Injector, Stealer, Worker as Deque};
+use disposal::{Disposal, Verbosity};
+use progress::Counters;
+use std::collections::BTreeMap;
+use std::io;
 use std::path::{Path, PathBuf};
-use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 #[derive(Parser, Debug)]
@@ -13,65 +24,115 @@ struct Args {
     #[clap(short, long, default_value_t = 4)]
     cores: usize,
 
-    /// Directory contains files to de-duplicate (non-recursive)
+    /// Directory contains files to de-duplicate
     #[clap(short, long, default_value = ".")]
     input_directory: String,
 
-}
+    /// Recurse into subdirectories instead of only scanning the top level
+    #[clap(short, long)]
+    recursive: bool,
 
-/// Worker used to remove duplicate files in `entries` by comparing SHA1 hashes.
-///
-/// This worker works in chunks. It will deduplicate files in `entries[start:start + count]`
-fn worker(entries: Arc<Vec<DirEntry>>, start: usize, count: usize, core_id: CoreId) 
-        -> BTreeMap<Vec<u8>, PathBuf> {
-    // Pin this worker to this core
-    core_affinity::set_for_current(core_id);
+    /// Files at or above this size (in bytes) are hashed from a memory
+    /// mapping instead of a buffered reader
+    #[clap(long, default_value_t = 256 * 1024 * 1024)]
+    mmap_threshold: u64,
 
-    // Collection of unique entries, used to determine if an entry has been seen already 
-    let mut seen = BTreeMap::new();
+    /// Files at or above this size (in bytes) are hashed in segments across
+    /// a rayon pool instead of with a single thread
+    #[clap(long, default_value_t = 1024 * 1024 * 1024)]
+    parallel_hash_threshold: u64,
 
-    // Timer used for printing progress
-    let time_start = std::time::Instant::now();
+    /// Log every file that would be removed, without touching anything
+    #[clap(long)]
+    dry_run: bool,
 
-    type BlakeHasher = Blake2b<U16>;
+    /// Move duplicates into this directory instead of deleting them
+    #[clap(long)]
+    output: Option<PathBuf>,
 
-    for (i, entry) in entries.iter().skip(start).take(count).enumerate() {
-        // Basic log to show progress of this worker
-        if i > 0 && i % 1000 == 0 {
-            let elapsed = time_start.elapsed();
-            println!("{core_id:?}: {i}/{count}: {:6.2} entry/sec",  
-                1.0 / (elapsed.as_secs_f64() / i as f64));
-        }
+    /// Print per-file detail (-v); pass twice (-vv) for extra detail like size
+    #[clap(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
 
-        // Get a hasher for the current entry
-        let mut hasher = BlakeHasher::new();
+/// Result of a worker's pass over its share of the candidates: the paths it
+/// kept, grouped by hash, and any disposal failures it hit along the way.
+struct WorkerResult {
+    seen: BTreeMap<Vec<u8>, Vec<PathBuf>>,
+    errors: Vec<(PathBuf, io::Error)>,
+}
 
-        // Get the path of the current entry
-        let entry_path = entry.path();
+/// Per-run configuration shared by every worker thread: the size thresholds
+/// that pick a hashing strategy, how duplicates get disposed of, and the
+/// counters used for progress reporting. Bundled into one struct (rather
+/// than threaded through `worker`'s argument list) so adding a new knob
+/// doesn't grow that list further.
+struct WorkerConfig {
+    mmap_threshold: u64,
+    parallel_hash_threshold: u64,
+    disposal: Arc<Disposal>,
+    counters: Arc<Counters>,
+}
 
-        // Ignore directories since this isn't a recursive worker
-        if entry_path.is_dir() {
-            continue;
-        }
+/// Worker used to remove duplicate files by comparing Blake2b hashes.
+///
+/// Pulls paths from its own deque, falling back to stealing from `injector`
+/// and from sibling workers' `stealers` once its local deque runs dry.
+///
+/// A hash match is only a candidate duplicate: `BlakeHasher` is a truncated
+/// 128-bit digest, so before deleting anything the candidate is compared
+/// byte-for-byte against every path already kept under that hash. Distinct
+/// files that happen to collide are both kept, as separate entries in the
+/// same bucket.
+fn worker(
+    local: Deque<PathBuf>,
+    injector: Arc<Injector<PathBuf>>,
+    stealers: Arc<Vec<Stealer<PathBuf>>>,
+    core_id: CoreId,
+    config: Arc<WorkerConfig>,
+) -> WorkerResult {
+    // Pin this worker to this core
+    core_affinity::set_for_current(core_id);
 
-        // Read the contents of the current entry
-        let entry_data = std::fs::read(&entry_path);
-        if entry_data.is_err() {
-            println!("Data Error: {entry_path:?} {entry_data:?}");
-            continue;
-        }
+    // Collection of unique entries, used to determine if an entry has been seen already
+    let mut seen = BTreeMap::new();
+    let mut errors = Vec::new();
+
+    while let Some(entry_path) = scheduler::next_task(&local, &injector, &stealers) {
+        // Get the hash of the entry contents, streaming the file rather than
+        // loading it whole so memory stays bounded for large files
+        let (val, len) = match hashing::hash_file(
+            &entry_path,
+            config.mmap_threshold,
+            config.parallel_hash_threshold,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                println!("Data Error: {entry_path:?} {e:?}");
+                continue;
+            }
+        };
 
-        // Get the SHA1 of the entry contents
-        hasher.update(&entry_data.unwrap());
-        let val = hasher.finalize();
+        config.counters.processed.fetch_add(1, Ordering::Relaxed);
+        config.counters.bytes.fetch_add(len, Ordering::Relaxed);
 
-        // If this file has been seen before, move it to the duplicate dir
-        if let Some(old_path) = seen.insert(val, entry_path) {
-            std::fs::remove_file(old_path).expect("Failed to remove file");
-        } 
+        // Compare against every path already kept under this hash. A real
+        // match gets disposed of; a collision is kept alongside the others.
+        let bucket: &mut Vec<PathBuf> = seen.entry(val).or_default();
+        let is_duplicate = bucket
+            .iter()
+            .any(|kept| verify::files_identical(kept, &entry_path).unwrap_or(false));
+
+        if is_duplicate {
+            if let Err(e) = config.disposal.dispose(&entry_path) {
+                errors.push((entry_path, e));
+            }
+        } else {
+            bucket.push(entry_path);
+        }
     }
 
-    seen.iter().map(|(k, v)| (k.to_vec(), v.into())).collect()
+    WorkerResult { seen, errors }
 }
 
 fn main() {
@@ -81,53 +142,113 @@ fn main() {
     // Execute attempt to execute
     let path = Path::new(&args.input_directory);
 
-    // Get the number of entries in the directory
-    let entries: Vec<_> = path.read_dir().unwrap()
-        .map(|x| x.unwrap())
-        .collect();
-    let num = entries.len();
-
-    println!("Entries: {num}");
-
-    // Chunk size based on the number of wanted cores, rounding up so that the last core
-    // has fewer entries
-    let chunk_size = (entries.len() as f64 / args.cores as f64).ceil() as usize;
-
-    // Wrap read-only objects in Arc to pass to the worker threads
-    let entries  = Arc::new(entries);
+    // Cap rayon's global pool at the requested core count, so segment hashing
+    // of huge files can't out-parallel the `--cores` limit the rest of the
+    // tool already respects via core_affinity
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(args.cores)
+        .build_global()
+        .expect("Failed to configure rayon thread pool");
+
+    // Walk the directory tree, then drop every file whose size is unique
+    // before handing the survivors to the hashing workers
+    let found = scheduler::walk(path, args.recursive);
+    let num_found = found.len();
+    let candidates = sizes::candidates(found);
+    let candidates_len = candidates.len() as u64;
+
+    println!("Entries: {num_found} ({candidates_len} candidates after size filtering)");
+
+    let injector = Arc::new(Injector::new());
+    scheduler::seed(&injector, candidates);
+
+    // Give every worker its own deque and a view of every sibling's stealer so
+    // idle workers can steal from whichever deque still has work
+    let locals: Vec<_> = (0..args.cores).map(|_| Deque::new_fifo()).collect();
+    let stealers = Arc::new(locals.iter().map(Deque::stealer).collect::<Vec<_>>());
+
+    let disposal = Arc::new(Disposal::new(
+        args.dry_run,
+        args.output.clone(),
+        Verbosity::from_count(args.verbose),
+    ));
+
+    // Shared counters the workers bump as they go, reported by the dedicated
+    // progress thread rather than by each worker printing its own line
+    let counters = Arc::new(Counters::default());
+    let running = Arc::new(AtomicBool::new(true));
+    let progress_thread = progress::spawn(candidates_len, counters.clone(), running.clone());
+
+    let config = Arc::new(WorkerConfig {
+        mmap_threshold: args.mmap_threshold,
+        parallel_hash_threshold: args.parallel_hash_threshold,
+        disposal,
+        counters,
+    });
 
     // Create the collection of threads
     let mut threads = Vec::new();
 
-    // Start each core with the subsection of the total entries
-    for core in 0..args.cores {
+    // Start each core, pulling work from its own deque and stealing when empty
+    for (core, local) in locals.into_iter().enumerate() {
         let core_id = CoreId { id: usize::from(core) };
-        let entries  = entries.clone();
+        let injector = injector.clone();
+        let stealers = stealers.clone();
+        let config = config.clone();
 
-        let thread = std::thread::spawn(move ||  {
-            worker(entries, core * chunk_size, chunk_size, core_id)
-        });
+        let thread = std::thread::spawn(move || worker(local, injector, stealers, core_id, config));
 
         threads.push(thread);
     }
 
     let mut results = Vec::new();
-    let mut total_seen = BTreeSet::new();
+    let mut total_seen: BTreeMap<Vec<u8>, Vec<PathBuf>> = BTreeMap::new();
+    let mut remaining = 0;
+    let mut errors = Vec::new();
 
     // Join all threads
     for thread in threads {
         results.push(thread.join().unwrap());
     }
 
-    // Remove duplicate entries found by each core
+    // Signal the progress thread to print its final line and exit
+    running.store(false, Ordering::Relaxed);
+    progress_thread.join().unwrap();
+
+    // Remove duplicate entries found across cores, verifying byte-for-byte
+    // before deleting since two different cores' hash buckets may collide
     for result in results {
-        for (hash, entry) in result {
-            if !total_seen.insert(hash) {
-                std::fs::remove_file(entry).expect("Failed to remove file");
+        errors.extend(result.errors);
+
+        for (hash, paths) in result.seen {
+            let bucket = total_seen.entry(hash).or_default();
+
+            for entry_path in paths {
+                let is_duplicate = bucket
+                    .iter()
+                    .any(|kept| verify::files_identical(kept, &entry_path).unwrap_or(false));
+
+                if is_duplicate {
+                    if let Err(e) = disposal.dispose(&entry_path) {
+                        errors.push((entry_path, e));
+                    }
+                } else {
+                    bucket.push(entry_path);
+                    remaining += 1;
+                }
             }
         }
     }
 
+    // Report any disposal failures instead of panicking, so one bad file
+    // doesn't take the rest of a successful run down with it
+    for (path, error) in &errors {
+        println!("Error disposing of {path:?}: {error}");
+    }
+
     // Print remaining number of files
-    println!("Remaining files: {}", total_seen.len());
+    println!("Remaining files: {remaining}");
+    if !errors.is_empty() {
+        println!("{} file(s) could not be disposed of", errors.len());
+    }
 }
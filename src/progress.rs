@@ -0,0 +1,58 @@
+//! Aggregate progress reporting across all worker threads.
+//!
+//! Previously each worker printed its own `entry/sec` line every 1000 files,
+//! which interleaves into noise once more than one core is running and can't
+//! show a real ETA since no worker knows the global total. Instead, workers
+//! just bump a couple of shared atomics as they go, and one dedicated thread
+//! wakes up periodically to print aggregate throughput, a percentage against
+//! the number of files left to hash after the initial scan and size
+//! filtering, and an ETA.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// Shared counters that worker threads update as they process files.
+#[derive(Default)]
+pub struct Counters {
+    pub processed: AtomicU64,
+    pub bytes: AtomicU64,
+}
+
+/// Spawn the progress thread. It prints a status line every `TICK` while
+/// `running` is set, and exits after printing one final line once `running`
+/// is cleared by the caller.
+pub fn spawn(
+    total: u64,
+    counters: Arc<Counters>,
+    running: Arc<AtomicBool>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let start = Instant::now();
+
+        while running.load(Ordering::Relaxed) {
+            std::thread::sleep(TICK);
+            report(total, &counters, start);
+        }
+
+        report(total, &counters, start);
+    })
+}
+
+fn report(total: u64, counters: &Counters, start: Instant) {
+    let processed = counters.processed.load(Ordering::Relaxed);
+    let bytes = counters.bytes.load(Ordering::Relaxed);
+    let elapsed = start.elapsed().as_secs_f64();
+    let rate = processed as f64 / elapsed.max(f64::EPSILON);
+
+    let percent = if total > 0 { 100.0 * processed as f64 / total as f64 } else { 100.0 };
+    let remaining = (total.saturating_sub(processed)) as f64;
+    let eta = if rate > 0.0 { remaining / rate } else { 0.0 };
+
+    println!(
+        "{processed}/{total} ({percent:5.1}%) {rate:7.2} files/sec, {:.2} MiB read, ETA {eta:5.0}s",
+        bytes as f64 / (1024.0 * 1024.0),
+    );
+}
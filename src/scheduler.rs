@@ -0,0 +1,142 @@
+//! Work-stealing scheduler used to hand file paths out to worker threads.
+//!
+//! Instead of statically slicing the entry list into one block per core (which
+//! leaves idle cores whenever a block happens to contain the slow files), each
+//! worker owns a `Worker` deque and pops from it directly. When a worker's own
+//! deque runs dry it first tries to grab a fresh batch from the shared
+//! `Injector`, then falls back to stealing from its sibling workers. This
+//! keeps every core busy regardless of how file sizes are distributed across
+//! the tree.
+
+use crossbeam_deque::{Injector, Stealer, Worker};
+use std::path::PathBuf;
+
+/// Walk `path`, collecting every file it finds.
+///
+/// When `recursive` is `false` only the immediate contents of `path` are
+/// scanned, matching the original non-recursive behavior. When `recursive` is
+/// `true`, subdirectories are descended into as they're discovered.
+pub fn walk(path: &std::path::Path, recursive: bool) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    walk_into(path, recursive, &mut found);
+    found
+}
+
+fn walk_into(path: &std::path::Path, recursive: bool, found: &mut Vec<PathBuf>) {
+    let Ok(read_dir) = path.read_dir() else {
+        return;
+    };
+
+    for entry in read_dir {
+        let Ok(entry) = entry else { continue };
+        let entry_path = entry.path();
+
+        // `file_type()` reports the entry's own type without following a
+        // symlink, unlike `entry_path.is_dir()`. A symlinked directory that
+        // points back to an ancestor (e.g. `ln -s .. a/loop`) would otherwise
+        // make a recursive walk recurse forever; symlinked regular files are
+        // still safe to read and hash normally, so only directories need the
+        // extra check below.
+        let Ok(file_type) = entry.file_type() else { continue };
+
+        if file_type.is_symlink() {
+            if !entry_path.is_dir() {
+                found.push(entry_path);
+            }
+            continue;
+        }
+
+        if file_type.is_dir() {
+            if recursive {
+                walk_into(&entry_path, recursive, found);
+            }
+            continue;
+        }
+
+        found.push(entry_path);
+    }
+}
+
+/// Push every path in `paths` onto `injector` for the worker pool to consume.
+pub fn seed(injector: &Injector<PathBuf>, paths: Vec<PathBuf>) {
+    for path in paths {
+        injector.push(path);
+    }
+}
+
+/// Pop the next path for this worker to process, stealing from the injector
+/// or a sibling worker if the local deque is empty.
+///
+/// Returns `None` once the local deque, the injector, and every sibling are
+/// all observed empty.
+pub fn next_task(
+    local: &Worker<PathBuf>,
+    injector: &Injector<PathBuf>,
+    stealers: &[Stealer<PathBuf>],
+) -> Option<PathBuf> {
+    local.pop().or_else(|| {
+        std::iter::repeat_with(|| {
+            injector
+                .steal_batch_and_pop(local)
+                .or_else(|| stealers.iter().map(|s| s.steal()).collect())
+        })
+        .find(|s| !s.is_retry())
+        .and_then(|s| s.success())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    #[test]
+    fn symlinked_directory_pointing_at_an_ancestor_does_not_recurse_forever() {
+        let root = scratch_dir("symlink_loop");
+        let sub = root.join("a");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(sub.join("file.txt"), b"data").unwrap();
+        std::os::unix::fs::symlink(&root, sub.join("loop")).unwrap();
+
+        let found = walk(&root, true);
+
+        assert_eq!(found, vec![sub.join("file.txt")]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn symlinked_regular_file_is_still_found() {
+        let root = scratch_dir("symlink_file");
+        let target = root.join("real.txt");
+        std::fs::write(&target, b"data").unwrap();
+        let link = root.join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let mut found = walk(&root, false);
+        found.sort();
+
+        let mut expected = vec![target, link];
+        expected.sort();
+        assert_eq!(found, expected);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn next_task_drains_local_then_injector_then_sibling_stealer() {
+        let local = Worker::new_fifo();
+        let injector = Injector::new();
+        let other = Worker::<PathBuf>::new_fifo();
+        other.push(PathBuf::from("stolen"));
+        let stealers = vec![other.stealer()];
+
+        local.push(PathBuf::from("local"));
+        assert_eq!(next_task(&local, &injector, &stealers), Some(PathBuf::from("local")));
+
+        injector.push(PathBuf::from("injected"));
+        assert_eq!(next_task(&local, &injector, &stealers), Some(PathBuf::from("injected")));
+
+        assert_eq!(next_task(&local, &injector, &stealers), Some(PathBuf::from("stolen")));
+
+        assert_eq!(next_task(&local, &injector, &stealers), None);
+    }
+}
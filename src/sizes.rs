@@ -0,0 +1,30 @@
+//! Cheap size-based pre-filter so the (expensive) hashing workers only ever
+//! see files that actually have a chance of being duplicates.
+//!
+//! A file with a unique length cannot have a duplicate, so grouping by
+//! `metadata().len()` and dropping every group of size 1 skips the vast
+//! majority of reads on a typical directory, for the cost of a single `stat`
+//! per file.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Group `paths` by file size, keeping only the groups with two or more
+/// entries. Paths whose metadata can't be read are dropped.
+pub fn candidates(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut by_size: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+
+    for path in paths {
+        let Ok(metadata) = path.metadata() else {
+            continue;
+        };
+
+        by_size.entry(metadata.len()).or_default().push(path);
+    }
+
+    by_size
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .flatten()
+        .collect()
+}
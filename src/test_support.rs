@@ -0,0 +1,33 @@
+//! Shared fixture helpers for other modules' `#[cfg(test)]` blocks.
+//!
+//! Every test module that needs scratch files on disk was hand-rolling the
+//! same temp-dir-join + counter + pid naming scheme; centralizing it here
+//! keeps that one scheme in one place instead of drifting copy to copy.
+
+use std::fs::File;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Claim a fresh, empty scratch directory under the system temp dir, unique
+/// to this test run and process.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let dir =
+        std::env::temp_dir().join(format!("dedupe_files_test_{name}_{}_{id}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write `contents` to a fresh, uniquely-named file directly under the
+/// system temp dir, returning its path.
+pub fn write_temp(name: &str, contents: &[u8]) -> PathBuf {
+    let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let path =
+        std::env::temp_dir().join(format!("dedupe_files_test_{name}_{}_{id}", std::process::id()));
+    File::create(&path).unwrap().write_all(contents).unwrap();
+    path
+}
@@ -0,0 +1,104 @@
+//! Byte-for-byte comparison used as a final safety net before deleting a file.
+//!
+//! `BlakeHasher` is a 128-bit truncated digest, so two distinct files
+//! colliding is unlikely but not impossible on a large corpus. Since the hash
+//! match already narrows the comparison down to same-size candidates, reading
+//! both files through to confirm equality is cheap insurance against an
+//! accidental collision causing data loss.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+const COMPARE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Compare the contents of `a` and `b`, short-circuiting on the first
+/// differing byte.
+pub fn files_identical(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut reader_a = BufReader::new(File::open(a)?);
+    let mut reader_b = BufReader::new(File::open(b)?);
+
+    let mut buf_a = [0u8; COMPARE_CHUNK_SIZE];
+    let mut buf_b = [0u8; COMPARE_CHUNK_SIZE];
+
+    loop {
+        let read_a = read_fully(&mut reader_a, &mut buf_a)?;
+        let read_b = read_fully(&mut reader_b, &mut buf_b)?;
+
+        if read_a != read_b || buf_a[..read_a] != buf_b[..read_b] {
+            return Ok(false);
+        }
+
+        if read_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fill `buf` as much as possible, returning the number of bytes read (`0`
+/// only at EOF).
+fn read_fully(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+
+    while total < buf.len() {
+        match reader.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_temp;
+
+    #[test]
+    fn identical_contents_are_identical() {
+        let a = write_temp("a", b"hello world");
+        let b = write_temp("b", b"hello world");
+
+        assert!(files_identical(&a, &b).unwrap());
+
+        let _ = std::fs::remove_file(a);
+        let _ = std::fs::remove_file(b);
+    }
+
+    #[test]
+    fn differing_bytes_are_not_identical() {
+        let a = write_temp("a", b"hello world");
+        let b = write_temp("b", b"hello worlD");
+
+        assert!(!files_identical(&a, &b).unwrap());
+
+        let _ = std::fs::remove_file(a);
+        let _ = std::fs::remove_file(b);
+    }
+
+    #[test]
+    fn differing_lengths_are_not_identical() {
+        let a = write_temp("a", b"short");
+        let b = write_temp("b", b"a fair bit longer than that");
+
+        assert!(!files_identical(&a, &b).unwrap());
+
+        let _ = std::fs::remove_file(a);
+        let _ = std::fs::remove_file(b);
+    }
+
+    #[test]
+    fn contents_spanning_multiple_compare_chunks_are_identical() {
+        let contents = vec![0x42u8; COMPARE_CHUNK_SIZE * 2 + 17];
+        let a = write_temp("a", &contents);
+        let b = write_temp("b", &contents);
+
+        assert!(files_identical(&a, &b).unwrap());
+
+        let _ = std::fs::remove_file(a);
+        let _ = std::fs::remove_file(b);
+    }
+}